@@ -0,0 +1,115 @@
+use super::{ClassifiedResponse, ClassifyEos, ClassifyResponse};
+use http::{HeaderMap, Response};
+use std::fmt;
+
+const GRPC_STATUS_HEADER: &str = "grpc-status";
+const GRPC_MESSAGE_HEADER: &str = "grpc-message";
+
+/// Failure classification for gRPC responses and streams based on the `grpc-status` header
+/// (or trailer, for streaming RPCs) and any error returned by the wrapped service.
+///
+/// This is the default classifier used by [`TraceLayer::new_for_grpc`].
+///
+/// [`TraceLayer::new_for_grpc`]: crate::trace::TraceLayer::new_for_grpc
+#[derive(Clone, Debug, Default)]
+pub struct GrpcErrorsAsFailures {
+    _priv: (),
+}
+
+/// A [gRPC status code](https://grpc.io/docs/guides/status-codes/).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GrpcCode(pub i32);
+
+impl GrpcCode {
+    /// `OK`, the only non-failure gRPC status.
+    pub const OK: GrpcCode = GrpcCode(0);
+    /// `UNKNOWN`, used when the wrapped service returns an error rather than a `grpc-status`.
+    pub const UNKNOWN: GrpcCode = GrpcCode(2);
+}
+
+/// The failure classification produced by [`GrpcErrorsAsFailures`].
+#[derive(Debug)]
+pub struct GrpcFailureClass {
+    /// The gRPC status code.
+    pub code: GrpcCode,
+    /// The `grpc-message` that came with it, or the formatted error if the wrapped service
+    /// failed outright.
+    pub message: String,
+}
+
+impl ClassifyResponse for GrpcErrorsAsFailures {
+    type FailureClass = GrpcFailureClass;
+    type ClassifyEos = GrpcEos;
+
+    fn classify_response<B>(
+        self,
+        res: &Response<B>,
+    ) -> ClassifiedResponse<Self::FailureClass, Self::ClassifyEos> {
+        // Unary and grpc-web responses sometimes carry `grpc-status` directly in the headers;
+        // true streaming RPCs only know it once the stream ends, in the trailers.
+        match classify_grpc_status(res.headers()) {
+            Some(result) => ClassifiedResponse::Ready(result),
+            None => ClassifiedResponse::RequiresEos(GrpcEos::default()),
+        }
+    }
+
+    fn classify_error<E>(self, error: &E) -> Self::FailureClass
+    where
+        E: fmt::Display,
+    {
+        GrpcFailureClass {
+            code: GrpcCode::UNKNOWN,
+            message: error.to_string(),
+        }
+    }
+}
+
+/// [`ClassifyEos`] used by [`GrpcErrorsAsFailures`].
+#[derive(Debug, Default)]
+pub struct GrpcEos {
+    _priv: (),
+}
+
+impl ClassifyEos for GrpcEos {
+    type FailureClass = GrpcFailureClass;
+
+    fn classify_eos(self, trailers: Option<&HeaderMap>) -> Result<(), Self::FailureClass> {
+        match trailers.and_then(classify_grpc_status) {
+            Some(result) => result,
+            None => Ok(()),
+        }
+    }
+
+    fn classify_error<E>(self, error: &E) -> Self::FailureClass
+    where
+        E: fmt::Display,
+    {
+        GrpcFailureClass {
+            code: GrpcCode::UNKNOWN,
+            message: error.to_string(),
+        }
+    }
+}
+
+fn classify_grpc_status(headers: &HeaderMap) -> Option<Result<(), GrpcFailureClass>> {
+    let code = headers
+        .get(GRPC_STATUS_HEADER)?
+        .to_str()
+        .ok()?
+        .parse::<i32>()
+        .ok()?;
+
+    if code == GrpcCode::OK.0 {
+        Some(Ok(()))
+    } else {
+        let message = headers
+            .get(GRPC_MESSAGE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        Some(Err(GrpcFailureClass {
+            code: GrpcCode(code),
+            message,
+        }))
+    }
+}
@@ -0,0 +1,104 @@
+//! Tools for classifying responses, and errors returned by the wrapped [`Service`], as
+//! successes or failures.
+//!
+//! [`MakeClassifier`] is consulted once per request by [`TraceLayer`](crate::trace::TraceLayer)
+//! to get a [`ClassifyResponse`], which looks at the response (and, for streaming bodies, the
+//! trailers once the stream ends) to decide what to hand [`OnFailure`](crate::trace::OnFailure).
+//!
+//! [`Service`]: tower_service::Service
+
+mod grpc_errors_as_failures;
+mod server_errors_as_failures;
+mod shared_classifier;
+
+pub use self::grpc_errors_as_failures::{GrpcCode, GrpcErrorsAsFailures, GrpcFailureClass};
+pub use self::server_errors_as_failures::{ServerErrorsAsFailures, ServerErrorsFailureClass};
+pub use self::shared_classifier::SharedClassifier;
+
+use http::{HeaderMap, Request, Response};
+use std::fmt;
+
+/// Trait for producing a [`ClassifyResponse`] for a given request.
+///
+/// `E` is the error type of the [`Service`](tower_service::Service) being traced.
+pub trait MakeClassifier<E> {
+    /// The type used to classify failures.
+    type FailureClass;
+    /// The classifier used once a streaming response body has ended.
+    type ClassifyEos: ClassifyEos<FailureClass = Self::FailureClass>;
+    /// The classifier produced.
+    type Classifier: ClassifyResponse<
+        FailureClass = Self::FailureClass,
+        ClassifyEos = Self::ClassifyEos,
+    >;
+
+    /// Returns a classifier to use for the given request.
+    fn make_classifier<B>(&self, req: &Request<B>) -> Self::Classifier;
+}
+
+/// Trait for classifying responses, and errors returned by the wrapped service, as failures.
+pub trait ClassifyResponse {
+    /// The type used to classify failures.
+    type FailureClass;
+    /// The classifier used once a streaming response body has ended.
+    type ClassifyEos: ClassifyEos<FailureClass = Self::FailureClass>;
+
+    /// Classify a response.
+    ///
+    /// For streaming bodies whose outcome can only be known once the stream ends (for example
+    /// gRPC, where the real status arrives in the trailers), return
+    /// [`ClassifiedResponse::RequiresEos`] with a [`ClassifyEos`] to finish the job later.
+    fn classify_response<B>(
+        self,
+        res: &Response<B>,
+    ) -> ClassifiedResponse<Self::FailureClass, Self::ClassifyEos>;
+
+    /// Classify an error returned by the wrapped service.
+    ///
+    /// `E` only needs to implement [`fmt::Display`], not `'static`: the error is only ever
+    /// formatted via `error.to_string()` to put it into the failure class, never stored past
+    /// this call. This lets services that return borrowed error types (for example
+    /// `&'a MyError`) still be wrapped in a [`TraceLayer`](crate::trace::TraceLayer).
+    fn classify_error<E>(self, error: &E) -> Self::FailureClass
+    where
+        E: fmt::Display;
+}
+
+/// Trait for classifying the end of a streaming response body, once its trailers (if any)
+/// have arrived.
+pub trait ClassifyEos {
+    /// The type used to classify failures.
+    type FailureClass;
+
+    /// Classify the end of a stream.
+    fn classify_eos(self, trailers: Option<&HeaderMap>) -> Result<(), Self::FailureClass>;
+
+    /// Classify an error that occurred while producing the body.
+    ///
+    /// See [`ClassifyResponse::classify_error`] for why `E` only needs [`fmt::Display`].
+    fn classify_error<E>(self, error: &E) -> Self::FailureClass
+    where
+        E: fmt::Display;
+}
+
+/// The result of classifying a response via [`ClassifyResponse::classify_response`].
+#[derive(Debug)]
+pub enum ClassifiedResponse<FailureClass, ClassifyEosT> {
+    /// The response was classified immediately, as either a success or a failure.
+    Ready(Result<(), FailureClass>),
+    /// The response is a streaming body whose outcome can only be known once the stream
+    /// ends; use the given [`ClassifyEos`] then.
+    RequiresEos(ClassifyEosT),
+}
+
+/// Classify an error using `classify`, formatting it via its [`fmt::Display`] impl.
+///
+/// `E` is only required to implement [`fmt::Display`], not `'static` — see
+/// [`ClassifyResponse::classify_error`] for why.
+pub(crate) fn classify_error<C, E>(classify: C, error: &E) -> C::FailureClass
+where
+    C: ClassifyEos,
+    E: fmt::Display,
+{
+    classify.classify_error(error)
+}
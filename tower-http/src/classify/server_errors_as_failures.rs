@@ -0,0 +1,70 @@
+use super::{ClassifiedResponse, ClassifyEos, ClassifyResponse};
+use http::{HeaderMap, Response, StatusCode};
+use std::fmt;
+
+/// Failure classification for regular HTTP responses: any `5xx` status is classified as a
+/// failure, as is any error returned by the wrapped service.
+///
+/// This is the default classifier used by [`TraceLayer::new_for_http`].
+///
+/// [`TraceLayer::new_for_http`]: crate::trace::TraceLayer::new_for_http
+#[derive(Clone, Debug, Default)]
+pub struct ServerErrorsAsFailures {
+    _priv: (),
+}
+
+/// The failure classification produced by [`ServerErrorsAsFailures`].
+#[derive(Debug)]
+pub enum ServerErrorsFailureClass {
+    /// The response had a `5xx` status code.
+    StatusCode(StatusCode),
+    /// The wrapped service returned an error, formatted via `Display`.
+    Error(String),
+}
+
+impl ClassifyResponse for ServerErrorsAsFailures {
+    type FailureClass = ServerErrorsFailureClass;
+    type ClassifyEos = ServerErrorsEos;
+
+    fn classify_response<B>(
+        self,
+        res: &Response<B>,
+    ) -> ClassifiedResponse<Self::FailureClass, Self::ClassifyEos> {
+        if res.status().is_server_error() {
+            ClassifiedResponse::Ready(Err(ServerErrorsFailureClass::StatusCode(res.status())))
+        } else {
+            ClassifiedResponse::Ready(Ok(()))
+        }
+    }
+
+    fn classify_error<E>(self, error: &E) -> Self::FailureClass
+    where
+        E: fmt::Display,
+    {
+        ServerErrorsFailureClass::Error(error.to_string())
+    }
+}
+
+/// [`ClassifyEos`] used by [`ServerErrorsAsFailures`].
+///
+/// Regular HTTP responses carry no end-of-stream status, so the body always succeeds once the
+/// headers have already been classified.
+#[derive(Debug, Default)]
+pub struct ServerErrorsEos {
+    _priv: (),
+}
+
+impl ClassifyEos for ServerErrorsEos {
+    type FailureClass = ServerErrorsFailureClass;
+
+    fn classify_eos(self, _trailers: Option<&HeaderMap>) -> Result<(), Self::FailureClass> {
+        Ok(())
+    }
+
+    fn classify_error<E>(self, error: &E) -> Self::FailureClass
+    where
+        E: fmt::Display,
+    {
+        ServerErrorsFailureClass::Error(error.to_string())
+    }
+}
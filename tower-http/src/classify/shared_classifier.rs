@@ -0,0 +1,51 @@
+use super::{ClassifyResponse, MakeClassifier};
+use http::Request;
+use std::sync::Arc;
+
+/// A [`MakeClassifier`] that clones the same [`ClassifyResponse`] for every request.
+///
+/// This is what [`TraceLayer::new_for_http`] and [`TraceLayer::new_for_grpc`] use under the
+/// hood, since [`ServerErrorsAsFailures`] and [`GrpcErrorsAsFailures`] carry no per-request
+/// state.
+///
+/// [`TraceLayer::new_for_http`]: crate::trace::TraceLayer::new_for_http
+/// [`TraceLayer::new_for_grpc`]: crate::trace::TraceLayer::new_for_grpc
+/// [`ServerErrorsAsFailures`]: super::ServerErrorsAsFailures
+/// [`GrpcErrorsAsFailures`]: super::GrpcErrorsAsFailures
+#[derive(Debug)]
+pub struct SharedClassifier<C> {
+    classifier: Arc<C>,
+}
+
+impl<C> SharedClassifier<C> {
+    /// Create a new `SharedClassifier` from the given classifier.
+    pub fn new<E>(classifier: C) -> Self
+    where
+        Self: MakeClassifier<E>,
+    {
+        Self {
+            classifier: Arc::new(classifier),
+        }
+    }
+}
+
+impl<C> Clone for SharedClassifier<C> {
+    fn clone(&self) -> Self {
+        Self {
+            classifier: Arc::clone(&self.classifier),
+        }
+    }
+}
+
+impl<C, E> MakeClassifier<E> for SharedClassifier<C>
+where
+    C: ClassifyResponse + Clone,
+{
+    type FailureClass = C::FailureClass;
+    type ClassifyEos = C::ClassifyEos;
+    type Classifier = C;
+
+    fn make_classifier<B>(&self, _req: &Request<B>) -> Self::Classifier {
+        (*self.classifier).clone()
+    }
+}
@@ -0,0 +1,8 @@
+//! `tower-http` is a library that provides HTTP-specific middleware and utilities built on top
+//! of the [`tower`] and [`tower-service`] crates.
+//!
+//! [`tower`]: https://crates.io/crates/tower
+//! [`tower-service`]: https://crates.io/crates/tower-service
+
+pub mod classify;
+pub mod trace;
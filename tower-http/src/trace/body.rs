@@ -0,0 +1,192 @@
+use super::on_grpc_message::GrpcFrameDecoder;
+use super::{OnBodyChunk, OnEos, OnFailure, OnGrpcMessage};
+use crate::classify::ClassifyEos;
+use bytes::Buf;
+use http::HeaderMap;
+use http_body::{Body, Frame};
+use pin_project_lite::pin_project;
+use std::{
+    fmt,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tracing::{Level, Span};
+
+pin_project! {
+    /// Response body produced by [`Trace`](super::Trace).
+    ///
+    /// Drives [`OnBodyChunk`] on every `Data` frame and, for responses whose `Content-Type`
+    /// starts with `application/grpc`, reassembles gRPC messages out of those frames via
+    /// [`GrpcFrameDecoder`] and fires [`OnGrpcMessage`] once per decoded message. Once the
+    /// stream ends (trailers, or plain end-of-stream), classifies the outcome via the
+    /// [`ClassifyEos`] left over from [`ClassifyResponse::classify_response`] and runs
+    /// [`OnEos`]/[`OnFailure`].
+    ///
+    /// [`ClassifyResponse::classify_response`]: crate::classify::ClassifyResponse::classify_response
+    pub struct ResponseBody<B, ClassifyEosT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT> {
+        #[pin]
+        inner: B,
+        classify_eos: Option<ClassifyEosT>,
+        on_body_chunk: Option<OnBodyChunkT>,
+        on_eos: Option<OnEosT>,
+        on_failure: Option<OnFailureT>,
+        on_grpc_message: Option<OnGrpcMessageT>,
+        grpc_decoder: Option<GrpcFrameDecoder>,
+        span: Span,
+        response_start: Instant,
+        last_chunk: Instant,
+    }
+}
+
+impl<B, ClassifyEosT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT>
+    ResponseBody<B, ClassifyEosT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT>
+{
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        inner: B,
+        classify_eos: Option<ClassifyEosT>,
+        on_body_chunk: Option<OnBodyChunkT>,
+        on_eos: Option<OnEosT>,
+        on_failure: Option<OnFailureT>,
+        on_grpc_message: Option<OnGrpcMessageT>,
+        is_grpc: bool,
+        span: Span,
+    ) -> Self {
+        let now = Instant::now();
+        Self {
+            inner,
+            classify_eos,
+            on_body_chunk,
+            on_eos,
+            on_failure,
+            on_grpc_message,
+            grpc_decoder: is_grpc.then(GrpcFrameDecoder::new),
+            span,
+            response_start: now,
+            last_chunk: now,
+        }
+    }
+}
+
+impl<B, ClassifyEosT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT> Body
+    for ResponseBody<B, ClassifyEosT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT>
+where
+    B: Body,
+    B::Error: fmt::Display,
+    ClassifyEosT: ClassifyEos,
+    OnBodyChunkT: OnBodyChunk<B::Data>,
+    OnEosT: OnEos,
+    OnFailureT: OnFailure<ClassifyEosT::FailureClass>,
+    OnGrpcMessageT: OnGrpcMessage,
+{
+    type Data = B::Data;
+    type Error = B::Error;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let _guard = this.span.enter();
+
+        let result = ready!(this.inner.poll_frame(cx));
+
+        let chunk_latency = this.last_chunk.elapsed();
+        *this.last_chunk = Instant::now();
+        let stream_duration = this.response_start.elapsed();
+
+        match result {
+            Some(Ok(frame)) => {
+                if let Some(data) = frame.data_ref() {
+                    if let Some(on_body_chunk) = this.on_body_chunk.as_mut() {
+                        on_body_chunk.on_body_chunk(data, chunk_latency, this.span);
+                    }
+
+                    if let (Some(decoder), Some(on_grpc_message)) =
+                        (this.grpc_decoder.as_mut(), this.on_grpc_message.as_mut())
+                    {
+                        for message in decoder.decode(data.chunk()) {
+                            on_grpc_message.on_grpc_message(&message, this.span);
+                        }
+                    }
+                }
+
+                if let Some(trailers) = frame.trailers_ref() {
+                    finish_stream(
+                        this.classify_eos.take(),
+                        this.on_eos.take(),
+                        this.on_failure.take(),
+                        this.grpc_decoder.take(),
+                        Some(trailers),
+                        stream_duration,
+                        this.span,
+                    );
+                }
+
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Some(Err(error)) => {
+                if let Some(classify_eos) = this.classify_eos.take() {
+                    let failure_class = crate::classify::classify_error(classify_eos, &error);
+                    if let Some(mut on_failure) = this.on_failure.take() {
+                        on_failure.on_failure(failure_class, stream_duration, this.span);
+                    }
+                }
+                Poll::Ready(Some(Err(error)))
+            }
+            None => {
+                finish_stream(
+                    this.classify_eos.take(),
+                    this.on_eos.take(),
+                    this.on_failure.take(),
+                    this.grpc_decoder.take(),
+                    None,
+                    stream_duration,
+                    this.span,
+                );
+                Poll::Ready(None)
+            }
+        }
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+}
+
+/// Runs the end-of-stream classification/callbacks shared by the trailers-frame and
+/// no-more-frames cases.
+#[allow(clippy::too_many_arguments)]
+fn finish_stream<ClassifyEosT, OnEosT, OnFailureT>(
+    classify_eos: Option<ClassifyEosT>,
+    on_eos: Option<OnEosT>,
+    on_failure: Option<OnFailureT>,
+    grpc_decoder: Option<GrpcFrameDecoder>,
+    trailers: Option<&HeaderMap>,
+    stream_duration: Duration,
+    span: &Span,
+) where
+    ClassifyEosT: ClassifyEos,
+    OnEosT: OnEos,
+    OnFailureT: OnFailure<ClassifyEosT::FailureClass>,
+{
+    if let Some(classify_eos) = classify_eos {
+        if let Err(failure_class) = classify_eos.classify_eos(trailers) {
+            if let Some(mut on_failure) = on_failure {
+                on_failure.on_failure(failure_class, stream_duration, span);
+            }
+        }
+    }
+
+    if let Some(on_eos) = on_eos {
+        on_eos.on_eos(trailers, stream_duration, span);
+    }
+
+    if let Some(decoder) = grpc_decoder {
+        let message_count = decoder.message_count();
+        span.in_scope(|| {
+            tracing::event!(Level::DEBUG, message_count, "gRPC stream ended");
+        });
+    }
+}
@@ -1,10 +1,12 @@
+use super::propagation::TraceContextMakeSpan;
 use super::{
-    DefaultMakeSpan, DefaultOnBodyChunk, DefaultOnEos, DefaultOnFailure, DefaultOnRequest,
-    DefaultOnResponse, Trace,
+    DefaultMakeSpan, DefaultOnBodyChunk, DefaultOnEos, DefaultOnFailure, DefaultOnGrpcMessage,
+    DefaultOnRequest, DefaultOnResponse, Trace,
 };
 use crate::classify::{
     GrpcErrorsAsFailures, MakeClassifier, ServerErrorsAsFailures, SharedClassifier,
 };
+use crate::trace::sampler::AlwaysSample;
 use std::{fmt, marker::PhantomData};
 use tower_layer::Layer;
 
@@ -24,6 +26,8 @@ pub struct TraceLayer<
     OnBodyChunk = DefaultOnBodyChunk,
     OnEos = DefaultOnEos,
     OnFailure = DefaultOnFailure,
+    OnGrpcMessage = DefaultOnGrpcMessage,
+    Sampler = AlwaysSample,
 > {
     pub(crate) make_classifier: M,
     pub(crate) make_span: MakeSpan,
@@ -32,11 +36,38 @@ pub struct TraceLayer<
     pub(crate) on_body_chunk: OnBodyChunk,
     pub(crate) on_eos: OnEos,
     pub(crate) on_failure: OnFailure,
+    pub(crate) on_grpc_message: OnGrpcMessage,
+    pub(crate) sampler: Sampler,
+    // Note: `E` is intentionally not required to be `'static` here. The classify + trace
+    // chain (`MakeClassifier::Classifier`, `classify_error`, and the `OnFailure` callback)
+    // only ever calls `error.to_string()` on failures, so `E: fmt::Display` is sufficient.
     pub(crate) _error: PhantomData<fn() -> E>,
 }
 
-impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure> Clone
-    for TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
+impl<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > Clone
+    for TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    >
 where
     M: Clone,
     MakeSpan: Clone,
@@ -45,6 +76,8 @@ where
     OnEos: Clone,
     OnBodyChunk: Clone,
     OnFailure: Clone,
+    OnGrpcMessage: Clone,
+    Sampler: Clone,
 {
     fn clone(&self) -> Self {
         Self {
@@ -53,6 +86,8 @@ where
             on_failure: self.on_failure.clone(),
             on_eos: self.on_eos.clone(),
             on_body_chunk: self.on_body_chunk.clone(),
+            on_grpc_message: self.on_grpc_message.clone(),
+            sampler: self.sampler.clone(),
             make_span: self.make_span.clone(),
             make_classifier: self.make_classifier.clone(),
             _error: self._error,
@@ -62,6 +97,12 @@ where
 
 impl<M, E> TraceLayer<M, E> {
     /// Create a new [`TraceLayer`] using the given [`MakeClassifier`].
+    ///
+    /// `E` only needs to implement [`fmt::Display`], it does not need to be `'static`. The
+    /// classifier chain only ever formats the error to put it into the span's fields, so
+    /// services that return borrowed error types (for example `&'a MyError`, or an error
+    /// holding a borrowed slice) can still be wrapped without cloning the error just to
+    /// satisfy tracing.
     pub fn new(make_classifier: M) -> Self
     where
         M: MakeClassifier<E>,
@@ -74,13 +115,37 @@ impl<M, E> TraceLayer<M, E> {
             on_eos: DefaultOnEos::default(),
             on_body_chunk: DefaultOnBodyChunk::default(),
             on_response: DefaultOnResponse::default(),
+            on_grpc_message: DefaultOnGrpcMessage::default(),
+            sampler: AlwaysSample::default(),
             _error: PhantomData,
         }
     }
 }
 
-impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
-    TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
+impl<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    >
+    TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    >
 {
     /// Customize what to do when a request is received.
     ///
@@ -90,12 +155,25 @@ impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
     pub fn on_request<NewOnRequest>(
         self,
         new_on_request: NewOnRequest,
-    ) -> TraceLayer<M, E, MakeSpan, NewOnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure> {
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        NewOnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
         TraceLayer {
             on_request: new_on_request,
             on_failure: self.on_failure,
             on_eos: self.on_eos,
             on_body_chunk: self.on_body_chunk,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
             make_span: self.make_span,
             on_response: self.on_response,
             make_classifier: self.make_classifier,
@@ -111,12 +189,25 @@ impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
     pub fn on_response<NewOnResponse>(
         self,
         new_on_response: NewOnResponse,
-    ) -> TraceLayer<M, E, MakeSpan, OnRequest, NewOnResponse, OnBodyChunk, OnEos, OnFailure> {
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        NewOnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
         TraceLayer {
             on_response: new_on_response,
             on_request: self.on_request,
             on_eos: self.on_eos,
             on_body_chunk: self.on_body_chunk,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
             on_failure: self.on_failure,
             make_span: self.make_span,
             make_classifier: self.make_classifier,
@@ -132,12 +223,25 @@ impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
     pub fn on_body_chunk<NewOnBodyChunk>(
         self,
         new_on_body_chunk: NewOnBodyChunk,
-    ) -> TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, NewOnBodyChunk, OnEos, OnFailure> {
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        NewOnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
         TraceLayer {
             on_body_chunk: new_on_body_chunk,
             on_eos: self.on_eos,
             on_failure: self.on_failure,
             on_request: self.on_request,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
             make_span: self.make_span,
             on_response: self.on_response,
             make_classifier: self.make_classifier,
@@ -153,12 +257,25 @@ impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
     pub fn on_eos<NewOnEos>(
         self,
         new_on_eos: NewOnEos,
-    ) -> TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, NewOnEos, OnFailure> {
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        NewOnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
         TraceLayer {
             on_eos: new_on_eos,
             on_body_chunk: self.on_body_chunk,
             on_failure: self.on_failure,
             on_request: self.on_request,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
             make_span: self.make_span,
             on_response: self.on_response,
             make_classifier: self.make_classifier,
@@ -174,12 +291,67 @@ impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
     pub fn on_failure<NewOnFailure>(
         self,
         new_on_failure: NewOnFailure,
-    ) -> TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, NewOnFailure> {
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        NewOnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
         TraceLayer {
             on_failure: new_on_failure,
             on_request: self.on_request,
             on_eos: self.on_eos,
             on_body_chunk: self.on_body_chunk,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
+            make_span: self.make_span,
+            on_response: self.on_response,
+            make_classifier: self.make_classifier,
+            _error: self._error,
+        }
+    }
+
+    /// Customize what to do when a gRPC message has been fully decoded from a streaming
+    /// response body. Request bodies are not decoded.
+    ///
+    /// This only fires for responses whose `Content-Type` starts with `application/grpc`,
+    /// regardless of whether the layer was built via [`TraceLayer::new_for_grpc`] or
+    /// [`TraceLayer::new_for_http`]; unlike [`on_body_chunk`] it is called once per complete
+    /// protobuf message rather than once per raw `Data` frame, since a single message can span
+    /// several frames and several messages can arrive in one frame.
+    ///
+    /// `NewOnGrpcMessage` is expected to implement [`OnGrpcMessage`].
+    ///
+    /// [`on_body_chunk`]: TraceLayer::on_body_chunk
+    /// [`OnGrpcMessage`]: super::OnGrpcMessage
+    pub fn on_grpc_message<NewOnGrpcMessage>(
+        self,
+        new_on_grpc_message: NewOnGrpcMessage,
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        NewOnGrpcMessage,
+        Sampler,
+    > {
+        TraceLayer {
+            on_grpc_message: new_on_grpc_message,
+            on_failure: self.on_failure,
+            on_request: self.on_request,
+            on_eos: self.on_eos,
+            on_body_chunk: self.on_body_chunk,
+            sampler: self.sampler,
             make_span: self.make_span,
             on_response: self.on_response,
             make_classifier: self.make_classifier,
@@ -196,18 +368,124 @@ impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
     pub fn make_span_with<NewMakeSpan>(
         self,
         new_make_span: NewMakeSpan,
-    ) -> TraceLayer<M, E, NewMakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure> {
+    ) -> TraceLayer<
+        M,
+        E,
+        NewMakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
         TraceLayer {
             make_span: new_make_span,
             on_request: self.on_request,
             on_failure: self.on_failure,
             on_body_chunk: self.on_body_chunk,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
             on_eos: self.on_eos,
             on_response: self.on_response,
             make_classifier: self.make_classifier,
             _error: self._error,
         }
     }
+
+    /// Wrap the current [`MakeSpan`] so spans created by this layer extract an incoming
+    /// [W3C Trace Context] `traceparent` header and are established as remote children of it,
+    /// rather than as new root traces.
+    ///
+    /// Pair this with [`PropagateTraceContextLayer`] on whichever service makes the next hop
+    /// in the call chain. The extracted context is only recorded on the span, not stored in
+    /// the request's extensions, so the outbound request carries this trace's ids only if the
+    /// [`TraceContext`] is inserted into its extensions before it reaches that layer.
+    ///
+    /// Works out of the box on top of the default [`MakeSpan`] (i.e.
+    /// `TraceLayer::new_for_http().with_trace_context_propagation()`). If you call
+    /// [`make_span_with`] with your own `MakeSpan` first, it must declare `trace_id`,
+    /// `span_id` and `trace_sampled` as empty fields for the recorded ids to show up — see
+    /// [`TraceContextMakeSpan`] for details.
+    ///
+    /// [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+    /// [`MakeSpan`]: super::MakeSpan
+    /// [`make_span_with`]: TraceLayer::make_span_with
+    /// [`TraceContextMakeSpan`]: super::propagation::TraceContextMakeSpan
+    /// [`TraceContext`]: super::propagation::TraceContext
+    /// [`PropagateTraceContextLayer`]: super::propagation::PropagateTraceContextLayer
+    pub fn with_trace_context_propagation(
+        self,
+    ) -> TraceLayer<
+        M,
+        E,
+        TraceContextMakeSpan<MakeSpan>,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > {
+        TraceLayer {
+            make_span: TraceContextMakeSpan::with_make_span(self.make_span),
+            on_request: self.on_request,
+            on_failure: self.on_failure,
+            on_body_chunk: self.on_body_chunk,
+            on_grpc_message: self.on_grpc_message,
+            sampler: self.sampler,
+            on_eos: self.on_eos,
+            on_response: self.on_response,
+            make_classifier: self.make_classifier,
+            _error: self._error,
+        }
+    }
+
+    /// Consult `new_sampler` once per request, before a span is created, to decide whether
+    /// this request should be traced at all.
+    ///
+    /// When the sampler returns `false` the layer skips [`MakeSpan`] and every `on_*`
+    /// callback for that request and just forwards it to the inner service, bounding the
+    /// tracing overhead on high-throughput services. See the [`sampler`] module for built-in
+    /// samplers (a fixed [`RatioSampler`] and a [`PerSecondSampler`] budget).
+    ///
+    /// `NewSampler` is expected to implement [`Sampler`].
+    ///
+    /// [`MakeSpan`]: super::MakeSpan
+    /// [`sampler`]: super::sampler
+    /// [`RatioSampler`]: super::sampler::RatioSampler
+    /// [`PerSecondSampler`]: super::sampler::PerSecondSampler
+    /// [`Sampler`]: super::sampler::Sampler
+    pub fn sample_with<NewSampler>(
+        self,
+        new_sampler: NewSampler,
+    ) -> TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        NewSampler,
+    > {
+        TraceLayer {
+            sampler: new_sampler,
+            on_request: self.on_request,
+            on_failure: self.on_failure,
+            on_body_chunk: self.on_body_chunk,
+            on_grpc_message: self.on_grpc_message,
+            on_eos: self.on_eos,
+            on_response: self.on_response,
+            make_span: self.make_span,
+            make_classifier: self.make_classifier,
+            _error: self._error,
+        }
+    }
 }
 
 impl<E> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, E> {
@@ -222,6 +500,8 @@ impl<E> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, E> {
             on_body_chunk: DefaultOnBodyChunk::default(),
             on_eos: DefaultOnEos::default(),
             on_failure: DefaultOnFailure::default(),
+            on_grpc_message: DefaultOnGrpcMessage::default(),
+            sampler: AlwaysSample::default(),
             _error: PhantomData,
         }
     }
@@ -230,6 +510,11 @@ impl<E> TraceLayer<SharedClassifier<ServerErrorsAsFailures>, E> {
 impl<E> TraceLayer<SharedClassifier<GrpcErrorsAsFailures>, E> {
     /// Create a new [`TraceLayer`] using [`GrpcErrorsAsFailures`] which supports classifying
     /// gRPC responses and streams based on the `grpc-status` header.
+    ///
+    /// Use [`on_grpc_message`] to additionally observe per-message stream events (message
+    /// counts and sizes) instead of opaque body-chunk events.
+    ///
+    /// [`on_grpc_message`]: TraceLayer::on_grpc_message
     pub fn new_for_grpc() -> Self {
         Self {
             make_classifier: SharedClassifier::new::<E>(GrpcErrorsAsFailures::default()),
@@ -239,13 +524,38 @@ impl<E> TraceLayer<SharedClassifier<GrpcErrorsAsFailures>, E> {
             on_body_chunk: DefaultOnBodyChunk::default(),
             on_eos: DefaultOnEos::default(),
             on_failure: DefaultOnFailure::default(),
+            on_grpc_message: DefaultOnGrpcMessage::default(),
+            sampler: AlwaysSample::default(),
             _error: PhantomData,
         }
     }
 }
 
-impl<S, M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure> Layer<S>
-    for TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
+impl<
+        S,
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > Layer<S>
+    for TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    >
 where
     M: Clone,
     MakeSpan: Clone,
@@ -254,8 +564,22 @@ where
     OnEos: Clone,
     OnBodyChunk: Clone,
     OnFailure: Clone,
+    OnGrpcMessage: Clone,
+    Sampler: Clone,
 {
-    type Service = Trace<S, M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>;
+    type Service = Trace<
+        S,
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    >;
 
     fn layer(&self, inner: S) -> Self::Service {
         Trace {
@@ -265,6 +589,8 @@ where
             on_request: self.on_request.clone(),
             on_eos: self.on_eos.clone(),
             on_body_chunk: self.on_body_chunk.clone(),
+            on_grpc_message: self.on_grpc_message.clone(),
+            sampler: self.sampler.clone(),
             on_response: self.on_response.clone(),
             on_failure: self.on_failure.clone(),
             _error: PhantomData,
@@ -272,8 +598,30 @@ where
     }
 }
 
-impl<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure> fmt::Debug
-    for TraceLayer<M, E, MakeSpan, OnRequest, OnResponse, OnBodyChunk, OnEos, OnFailure>
+impl<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    > fmt::Debug
+    for TraceLayer<
+        M,
+        E,
+        MakeSpan,
+        OnRequest,
+        OnResponse,
+        OnBodyChunk,
+        OnEos,
+        OnFailure,
+        OnGrpcMessage,
+        Sampler,
+    >
 where
     M: fmt::Debug,
     MakeSpan: fmt::Debug,
@@ -282,6 +630,8 @@ where
     OnEos: fmt::Debug,
     OnBodyChunk: fmt::Debug,
     OnFailure: fmt::Debug,
+    OnGrpcMessage: fmt::Debug,
+    Sampler: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("TraceLayer")
@@ -292,6 +642,8 @@ where
             .field("on_body_chunk", &self.on_body_chunk)
             .field("on_eos", &self.on_eos)
             .field("on_failure", &self.on_failure)
+            .field("on_grpc_message", &self.on_grpc_message)
+            .field("sampler", &self.sampler)
             .finish()
     }
-}
\ No newline at end of file
+}
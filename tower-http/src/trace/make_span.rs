@@ -0,0 +1,113 @@
+use http::Request;
+use tracing::{field::Empty, Level, Span};
+
+/// Trait used to generate [`Span`]s from requests, that all other [`Trace`] callbacks will be
+/// called inside.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+pub trait MakeSpan<B> {
+    /// Make a span from a request.
+    fn make_span(&mut self, request: &Request<B>) -> Span;
+}
+
+impl<B> MakeSpan<B> for Span {
+    fn make_span(&mut self, _request: &Request<B>) -> Span {
+        self.clone()
+    }
+}
+
+impl<F, B> MakeSpan<B> for F
+where
+    F: FnMut(&Request<B>) -> Span,
+{
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        self(request)
+    }
+}
+
+/// The default way [`Span`]s are created for [`TraceLayer`](super::TraceLayer). Creates a
+/// span named `"request"` at the given [`Level`] (`DEBUG` by default), recording the
+/// request's method, URI and version.
+///
+/// The span also pre-declares empty `trace_id`, `span_id` and `trace_sampled` fields, so that
+/// [`TraceContextMakeSpan`] can fill them in when
+/// [`TraceLayer::with_trace_context_propagation`] is used on top of this `MakeSpan`.
+///
+/// [`TraceContextMakeSpan`]: super::propagation::TraceContextMakeSpan
+/// [`TraceLayer::with_trace_context_propagation`]: super::TraceLayer::with_trace_context_propagation
+#[derive(Debug, Clone)]
+pub struct DefaultMakeSpan {
+    level: Level,
+    include_headers: bool,
+}
+
+impl DefaultMakeSpan {
+    /// Create a new `DefaultMakeSpan`.
+    pub fn new() -> Self {
+        Self {
+            level: Level::DEBUG,
+            include_headers: false,
+        }
+    }
+
+    /// Set the [`Level`] used for the created span.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Include request headers on the created span.
+    pub fn include_headers(mut self, include_headers: bool) -> Self {
+        self.include_headers = include_headers;
+        self
+    }
+}
+
+impl Default for DefaultMakeSpan {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> MakeSpan<B> for DefaultMakeSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        macro_rules! make_span {
+            ($level:expr) => {
+                if self.include_headers {
+                    tracing::span!(
+                        $level,
+                        "request",
+                        method = %request.method(),
+                        uri = %request.uri(),
+                        version = ?request.version(),
+                        headers = ?request.headers(),
+                        trace_id = Empty,
+                        span_id = Empty,
+                        trace_sampled = Empty,
+                    )
+                } else {
+                    tracing::span!(
+                        $level,
+                        "request",
+                        method = %request.method(),
+                        uri = %request.uri(),
+                        version = ?request.version(),
+                        trace_id = Empty,
+                        span_id = Empty,
+                        trace_sampled = Empty,
+                    )
+                }
+            };
+        }
+
+        match self.level {
+            Level::ERROR => make_span!(Level::ERROR),
+            Level::WARN => make_span!(Level::WARN),
+            Level::INFO => make_span!(Level::INFO),
+            Level::DEBUG => make_span!(Level::DEBUG),
+            Level::TRACE => make_span!(Level::TRACE),
+        }
+    }
+}
@@ -0,0 +1,37 @@
+//! Middleware that adds high level [tracing] to a [`Service`].
+//!
+//! # Example
+//!
+//! ```
+//! use tower_http::trace::TraceLayer;
+//! # type MyError = std::io::Error;
+//!
+//! let layer = TraceLayer::<_, MyError>::new_for_http();
+//! ```
+//!
+//! [tracing]: https://crates.io/crates/tracing
+//! [`Service`]: tower_service::Service
+
+mod body;
+mod layer;
+mod make_span;
+mod on_body_chunk;
+mod on_eos;
+mod on_failure;
+mod on_grpc_message;
+mod on_request;
+mod on_response;
+pub mod propagation;
+pub mod sampler;
+mod service;
+
+pub use self::body::ResponseBody;
+pub use self::layer::TraceLayer;
+pub use self::make_span::{DefaultMakeSpan, MakeSpan};
+pub use self::on_body_chunk::{DefaultOnBodyChunk, OnBodyChunk};
+pub use self::on_eos::{DefaultOnEos, OnEos};
+pub use self::on_failure::{DefaultOnFailure, OnFailure};
+pub use self::on_grpc_message::{DefaultOnGrpcMessage, GrpcMessageInfo, OnGrpcMessage};
+pub use self::on_request::{DefaultOnRequest, OnRequest};
+pub use self::on_response::{DefaultOnResponse, OnResponse};
+pub use self::service::Trace;
@@ -0,0 +1,41 @@
+use std::time::Duration;
+use tracing::Span;
+
+/// Trait used to tell [`Trace`] what to do when a body chunk has been sent.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+pub trait OnBodyChunk<B> {
+    /// Do something when a body chunk has been sent.
+    ///
+    /// `latency` is the duration since the previous chunk (or the response, for the first
+    /// chunk) was sent.
+    fn on_body_chunk(&mut self, chunk: &B, latency: Duration, span: &Span);
+}
+
+impl<B> OnBodyChunk<B> for () {
+    #[inline]
+    fn on_body_chunk(&mut self, _chunk: &B, _latency: Duration, _span: &Span) {}
+}
+
+impl<F, B> OnBodyChunk<B> for F
+where
+    F: FnMut(&B, Duration, &Span),
+{
+    fn on_body_chunk(&mut self, chunk: &B, latency: Duration, span: &Span) {
+        self(chunk, latency, span)
+    }
+}
+
+/// The default [`OnBodyChunk`] implementation used by [`TraceLayer`](super::TraceLayer).
+/// Does nothing.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultOnBodyChunk {
+    _priv: (),
+}
+
+impl<B> OnBodyChunk<B> for DefaultOnBodyChunk {
+    #[inline]
+    fn on_body_chunk(&mut self, _chunk: &B, _latency: Duration, _span: &Span) {}
+}
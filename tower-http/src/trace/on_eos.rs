@@ -0,0 +1,32 @@
+use http::HeaderMap;
+use std::time::Duration;
+use tracing::Span;
+
+/// Trait used to tell [`Trace`] what to do when a streaming response body has closed.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+pub trait OnEos {
+    /// Do something when a streaming response body has closed.
+    ///
+    /// `stream_duration` is the duration since the response was produced.
+    fn on_eos(self, trailers: Option<&HeaderMap>, stream_duration: Duration, span: &Span);
+}
+
+impl OnEos for () {
+    #[inline]
+    fn on_eos(self, _trailers: Option<&HeaderMap>, _stream_duration: Duration, _span: &Span) {}
+}
+
+/// The default [`OnEos`] implementation used by [`TraceLayer`](super::TraceLayer). Does
+/// nothing.
+#[derive(Clone, Debug, Default)]
+pub struct DefaultOnEos {
+    _priv: (),
+}
+
+impl OnEos for DefaultOnEos {
+    #[inline]
+    fn on_eos(self, _trailers: Option<&HeaderMap>, _stream_duration: Duration, _span: &Span) {}
+}
@@ -0,0 +1,82 @@
+use std::{fmt, time::Duration};
+use tracing::{Level, Span};
+
+/// Trait used to tell [`Trace`] what to do when a request has been classified as a failure.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+pub trait OnFailure<FailureClass> {
+    /// Do something with the failure classification.
+    fn on_failure(&mut self, failure_classification: FailureClass, latency: Duration, span: &Span);
+}
+
+impl<FailureClass> OnFailure<FailureClass> for () {
+    #[inline]
+    fn on_failure(
+        &mut self,
+        _failure_classification: FailureClass,
+        _latency: Duration,
+        _span: &Span,
+    ) {
+    }
+}
+
+/// The default [`OnFailure`] implementation used by [`TraceLayer`](super::TraceLayer). Logs
+/// the failure classification at `ERROR` level.
+#[derive(Clone, Debug)]
+pub struct DefaultOnFailure {
+    level: Level,
+}
+
+impl DefaultOnFailure {
+    /// Create a new `DefaultOnFailure`.
+    pub fn new() -> Self {
+        Self {
+            level: Level::ERROR,
+        }
+    }
+
+    /// Set the [`Level`] used for the failure log.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl Default for DefaultOnFailure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<FailureClass> OnFailure<FailureClass> for DefaultOnFailure
+where
+    FailureClass: fmt::Debug,
+{
+    fn on_failure(
+        &mut self,
+        failure_classification: FailureClass,
+        latency: Duration,
+        _span: &Span,
+    ) {
+        let latency = latency.as_micros();
+        match self.level {
+            Level::ERROR => {
+                tracing::event!(Level::ERROR, classification = ?failure_classification, latency, "response failed")
+            }
+            Level::WARN => {
+                tracing::event!(Level::WARN, classification = ?failure_classification, latency, "response failed")
+            }
+            Level::INFO => {
+                tracing::event!(Level::INFO, classification = ?failure_classification, latency, "response failed")
+            }
+            Level::DEBUG => {
+                tracing::event!(Level::DEBUG, classification = ?failure_classification, latency, "response failed")
+            }
+            Level::TRACE => {
+                tracing::event!(Level::TRACE, classification = ?failure_classification, latency, "response failed")
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use tracing::{Level, Span};
+
+/// Information about a single fully-assembled gRPC message, decoded from the
+/// Length-Prefixed-Message framing used by gRPC streams.
+///
+/// See the [module docs](crate::trace) for more details.
+#[derive(Debug)]
+pub struct GrpcMessageInfo {
+    /// The zero-based position of this message within the stream.
+    pub index: usize,
+    /// The length, in bytes, of the message payload (excluding the 5-byte LPM prefix).
+    pub length: usize,
+    /// Whether the compression flag was set on this message's LPM prefix.
+    pub compressed: bool,
+}
+
+/// Trait used to tell [`Trace`] what to do when a gRPC message has been fully decoded from
+/// a streaming response body. Request bodies are not decoded.
+///
+/// Unlike [`OnBodyChunk`] this fires once per complete protobuf message rather than once per
+/// raw `Data` frame, since a single message can span several frames and several messages can
+/// arrive in one frame.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+/// [`OnBodyChunk`]: super::OnBodyChunk
+pub trait OnGrpcMessage {
+    /// Do something with a fully decoded gRPC message.
+    ///
+    /// `message` describes the message that was just decoded and `span` is the span
+    /// produced by [`MakeSpan`].
+    ///
+    /// [`MakeSpan`]: super::MakeSpan
+    fn on_grpc_message(&mut self, message: &GrpcMessageInfo, span: &Span);
+}
+
+impl OnGrpcMessage for () {
+    #[inline]
+    fn on_grpc_message(&mut self, _: &GrpcMessageInfo, _: &Span) {}
+}
+
+impl<F> OnGrpcMessage for F
+where
+    F: FnMut(&GrpcMessageInfo, &Span),
+{
+    fn on_grpc_message(&mut self, message: &GrpcMessageInfo, span: &Span) {
+        self(message, span)
+    }
+}
+
+/// The default [`OnGrpcMessage`] implementation used by [`TraceLayer::new_for_grpc`].
+///
+/// Each decoded message is recorded as a `tracing` event at `DEBUG` level.
+///
+/// [`TraceLayer::new_for_grpc`]: super::TraceLayer::new_for_grpc
+#[derive(Clone, Debug, Default)]
+pub struct DefaultOnGrpcMessage {
+    _priv: (),
+}
+
+impl DefaultOnGrpcMessage {
+    /// Create a new `DefaultOnGrpcMessage`.
+    pub fn new() -> Self {
+        Self { _priv: () }
+    }
+}
+
+impl OnGrpcMessage for DefaultOnGrpcMessage {
+    fn on_grpc_message(&mut self, message: &GrpcMessageInfo, span: &Span) {
+        let _guard = span.enter();
+        tracing::event!(
+            Level::DEBUG,
+            message.index = message.index,
+            message.length = message.length,
+            message.compressed = message.compressed,
+            "decoded gRPC message"
+        );
+    }
+}
+
+/// The largest message payload `GrpcFrameDecoder` will buffer before giving up on a stream.
+///
+/// A message's declared length comes straight off the wire and is not otherwise bounded by the
+/// LPM framing (it fits in a `u32`, so up to ~4 GiB), so without a cap a peer could force this
+/// decoder to buffer unbounded data for a single in-flight message. `4 MiB` matches the default
+/// max message size used by common gRPC server implementations (e.g. tonic's).
+const MAX_MESSAGE_LEN: usize = 4 * 1024 * 1024;
+
+/// Incrementally decodes the gRPC Length-Prefixed-Message framing used by
+/// [`TraceLayer::new_for_grpc`] streams, reassembling complete messages across `Data` frame
+/// boundaries.
+///
+/// Wire format per message: `1` byte compression flag, `4` bytes big-endian length, then
+/// `length` bytes of payload.
+///
+/// [`TraceLayer::new_for_grpc`]: super::TraceLayer::new_for_grpc
+#[derive(Debug, Default)]
+pub(crate) struct GrpcFrameDecoder {
+    buf: Vec<u8>,
+    next_index: usize,
+    // Set once a message declares a length over `MAX_MESSAGE_LEN`; once poisoned this decoder
+    // stops reassembling messages for the rest of the stream rather than keep growing `buf`.
+    poisoned: bool,
+}
+
+impl GrpcFrameDecoder {
+    pub(crate) fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            next_index: 0,
+            poisoned: false,
+        }
+    }
+
+    /// Feed in a newly received `Data` frame's bytes, returning every message that could be
+    /// fully assembled from the buffered data so far (possibly none, possibly more than one).
+    pub(crate) fn decode(&mut self, chunk: &[u8]) -> Vec<GrpcMessageInfo> {
+        if self.poisoned {
+            return Vec::new();
+        }
+
+        self.buf.extend_from_slice(chunk);
+
+        let mut messages = Vec::new();
+        loop {
+            // 1 byte compression flag + 4 byte big-endian length.
+            if self.buf.len() < 5 {
+                break;
+            }
+
+            let compressed = self.buf[0] != 0;
+            let len =
+                u32::from_be_bytes([self.buf[1], self.buf[2], self.buf[3], self.buf[4]]) as usize;
+
+            if len > MAX_MESSAGE_LEN {
+                self.buf.clear();
+                self.poisoned = true;
+                break;
+            }
+
+            if self.buf.len() < 5 + len {
+                break;
+            }
+
+            let index = self.next_index;
+            self.next_index += 1;
+            self.buf.drain(0..5 + len);
+            messages.push(GrpcMessageInfo {
+                index,
+                length: len,
+                compressed,
+            });
+        }
+        messages
+    }
+
+    /// The number of messages decoded so far, to be recorded on the span at EOS.
+    pub(crate) fn message_count(&self) -> usize {
+        self.next_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_a_single_message_delivered_in_one_chunk() {
+        let mut decoder = GrpcFrameDecoder::new();
+        let mut frame = vec![0u8, 0, 0, 0, 3];
+        frame.extend_from_slice(b"abc");
+
+        let messages = decoder.decode(&frame);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].index, 0);
+        assert_eq!(messages[0].length, 3);
+        assert!(!messages[0].compressed);
+        assert_eq!(decoder.message_count(), 1);
+    }
+
+    #[test]
+    fn buffers_a_prefix_split_across_chunks() {
+        let mut decoder = GrpcFrameDecoder::new();
+        assert!(decoder.decode(&[0, 0, 0]).is_empty());
+        let messages = decoder.decode(&[0, 2, b'h', b'i']);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].length, 2);
+    }
+
+    #[test]
+    fn buffers_a_payload_split_across_chunks() {
+        let mut decoder = GrpcFrameDecoder::new();
+        let mut first = vec![0u8, 0, 0, 0, 5];
+        first.extend_from_slice(b"he");
+        assert!(decoder.decode(&first).is_empty());
+
+        let messages = decoder.decode(b"llo");
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].length, 5);
+    }
+
+    #[test]
+    fn decodes_multiple_messages_delivered_in_one_chunk() {
+        let mut decoder = GrpcFrameDecoder::new();
+        let mut frame = vec![0u8, 0, 0, 0, 1, b'a'];
+        frame.extend_from_slice(&[1, 0, 0, 0, 1, b'b']);
+
+        let messages = decoder.decode(&frame);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].index, 0);
+        assert!(!messages[0].compressed);
+        assert_eq!(messages[1].index, 1);
+        assert!(messages[1].compressed);
+        assert_eq!(decoder.message_count(), 2);
+    }
+
+    #[test]
+    fn stops_buffering_a_message_declaring_a_length_over_the_cap() {
+        let mut decoder = GrpcFrameDecoder::new();
+        let mut frame = vec![0u8, 0, 0, 0, 0];
+        frame[1..5].copy_from_slice(&(MAX_MESSAGE_LEN as u32 + 1).to_be_bytes());
+        frame.extend_from_slice(b"only a little of the oversized payload");
+
+        assert!(decoder.decode(&frame).is_empty());
+        assert_eq!(decoder.message_count(), 0);
+        // Further chunks for this (now poisoned) stream are dropped rather than buffered.
+        assert!(decoder.decode(b"more data").is_empty());
+    }
+}
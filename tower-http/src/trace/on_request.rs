@@ -0,0 +1,66 @@
+use http::Request;
+use tracing::{Level, Span};
+
+/// Trait used to tell [`Trace`] what to do when a request is received.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+pub trait OnRequest<B> {
+    /// Do something with the request.
+    fn on_request(&mut self, request: &Request<B>, span: &Span);
+}
+
+impl<B> OnRequest<B> for () {
+    #[inline]
+    fn on_request(&mut self, _request: &Request<B>, _span: &Span) {}
+}
+
+impl<F, B> OnRequest<B> for F
+where
+    F: FnMut(&Request<B>, &Span),
+{
+    fn on_request(&mut self, request: &Request<B>, span: &Span) {
+        self(request, span)
+    }
+}
+
+/// The default [`OnRequest`] implementation used by [`TraceLayer`](super::TraceLayer). Logs
+/// that a request has started processing, at `DEBUG` level.
+#[derive(Clone, Debug)]
+pub struct DefaultOnRequest {
+    level: Level,
+}
+
+impl DefaultOnRequest {
+    /// Create a new `DefaultOnRequest`.
+    pub fn new() -> Self {
+        Self {
+            level: Level::DEBUG,
+        }
+    }
+
+    /// Set the [`Level`] used for the request log.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl Default for DefaultOnRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> OnRequest<B> for DefaultOnRequest {
+    fn on_request(&mut self, _request: &Request<B>, _span: &Span) {
+        match self.level {
+            Level::ERROR => tracing::event!(Level::ERROR, "started processing request"),
+            Level::WARN => tracing::event!(Level::WARN, "started processing request"),
+            Level::INFO => tracing::event!(Level::INFO, "started processing request"),
+            Level::DEBUG => tracing::event!(Level::DEBUG, "started processing request"),
+            Level::TRACE => tracing::event!(Level::TRACE, "started processing request"),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use http::Response;
+use std::time::Duration;
+use tracing::{Level, Span};
+
+/// Trait used to tell [`Trace`] what to do when a response has been produced.
+///
+/// See the [module docs](crate::trace) for more details.
+///
+/// [`Trace`]: super::Trace
+pub trait OnResponse<B> {
+    /// Do something with the response.
+    ///
+    /// `latency` is the duration since the request was received.
+    fn on_response(self, response: &Response<B>, latency: Duration, span: &Span);
+}
+
+impl<B> OnResponse<B> for () {
+    #[inline]
+    fn on_response(self, _response: &Response<B>, _latency: Duration, _span: &Span) {}
+}
+
+/// The default [`OnResponse`] implementation used by [`TraceLayer`](super::TraceLayer). Logs
+/// that a response has been produced, at `DEBUG` level, along with its latency.
+#[derive(Clone, Debug)]
+pub struct DefaultOnResponse {
+    level: Level,
+}
+
+impl DefaultOnResponse {
+    /// Create a new `DefaultOnResponse`.
+    pub fn new() -> Self {
+        Self {
+            level: Level::DEBUG,
+        }
+    }
+
+    /// Set the [`Level`] used for the response log.
+    pub fn level(mut self, level: Level) -> Self {
+        self.level = level;
+        self
+    }
+}
+
+impl Default for DefaultOnResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<B> OnResponse<B> for DefaultOnResponse {
+    fn on_response(self, response: &Response<B>, latency: Duration, _span: &Span) {
+        let status = response.status().as_u16();
+        let latency = latency.as_micros();
+        match self.level {
+            Level::ERROR => {
+                tracing::event!(Level::ERROR, status, latency, "finished processing request")
+            }
+            Level::WARN => {
+                tracing::event!(Level::WARN, status, latency, "finished processing request")
+            }
+            Level::INFO => {
+                tracing::event!(Level::INFO, status, latency, "finished processing request")
+            }
+            Level::DEBUG => {
+                tracing::event!(Level::DEBUG, status, latency, "finished processing request")
+            }
+            Level::TRACE => {
+                tracing::event!(Level::TRACE, status, latency, "finished processing request")
+            }
+        }
+    }
+}
@@ -0,0 +1,358 @@
+//! [W3C Trace Context] propagation for [`TraceLayer`].
+//!
+//! [`TraceContextMakeSpan`] extracts an incoming `traceparent` header and establishes the
+//! [`Span`] created by [`TraceLayer`] as a remote child of it, so logs/spans line up across a
+//! distributed trace. [`PropagateTraceContextLayer`] is the client-side companion: it
+//! generates (or forwards) a [`TraceContext`] and serializes it back into a `traceparent`
+//! header on outgoing requests.
+//!
+//! [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+//! [`TraceLayer`]: super::TraceLayer
+//! [`Span`]: tracing::Span
+
+use super::{DefaultMakeSpan, MakeSpan};
+use http::{HeaderValue, Request};
+use std::{
+    fmt,
+    sync::atomic::{AtomicU64, Ordering},
+    task::{Context, Poll},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+use tracing::Span;
+
+/// The name of the W3C Trace Context header.
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+/// A parsed (or freshly generated) [W3C Trace Context].
+///
+/// [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceContext {
+    /// The 16-byte trace id, shared by every span in the distributed trace.
+    pub trace_id: [u8; 16],
+    /// The 8-byte id of the span that issued this request (the "parent" from the point of
+    /// view of whichever span is created next).
+    pub span_id: [u8; 8],
+    /// Whether the `sampled` flag was set.
+    pub sampled: bool,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header value of the form
+    /// `version-trace_id-parent_id-flags` (`2/32/16/2` hex digits, `-`-separated).
+    pub fn parse_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.split('-');
+        let version = parts.next()?;
+        let trace_id = parts.next()?;
+        let parent_id = parts.next()?;
+        let flags = parts.next()?;
+        if parts.next().is_some() {
+            return None;
+        }
+        if version.len() != 2 || trace_id.len() != 32 || parent_id.len() != 16 || flags.len() != 2 {
+            return None;
+        }
+        // `ff` is reserved and must never be used as a version.
+        if version.eq_ignore_ascii_case("ff") {
+            return None;
+        }
+
+        let _version = decode_hex::<1>(version)?;
+        let trace_id = decode_hex::<16>(trace_id)?;
+        let span_id = decode_hex::<8>(parent_id)?;
+        let flags = decode_hex::<1>(flags)?;
+
+        // All-zero trace/span ids are explicitly invalid per the spec.
+        if trace_id == [0; 16] || span_id == [0; 8] {
+            return None;
+        }
+
+        Some(Self {
+            trace_id,
+            span_id,
+            sampled: flags[0] & 0x01 == 1,
+        })
+    }
+
+    /// Generate a new, sampled root [`TraceContext`] (fresh trace id and span id).
+    ///
+    /// The ids are produced by a fast non-cryptographic generator; they're meant to be
+    /// unique enough for correlating spans within a trace, not to be unguessable.
+    pub fn generate() -> Self {
+        Self {
+            trace_id: generate_id(),
+            span_id: generate_id(),
+            sampled: true,
+        }
+    }
+
+    /// Render this context as a `traceparent` header value.
+    pub fn header_value(&self) -> HeaderValue {
+        let flags = if self.sampled { "01" } else { "00" };
+        let value = format!(
+            "00-{}-{}-{}",
+            encode_hex(&self.trace_id),
+            encode_hex(&self.span_id),
+            flags
+        );
+        HeaderValue::from_str(&value)
+            .expect("hex-encoded traceparent is always a valid header value")
+    }
+}
+
+fn decode_hex<const N: usize>(s: &str) -> Option<[u8; N]> {
+    // `from_str_radix` alone would also accept a leading `+`, which is not a hex digit.
+    if s.len() != N * 2 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let mut out = [0u8; N];
+    for (i, slot) in out.iter_mut().enumerate() {
+        *slot = u8::from_str_radix(s.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(out)
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    use fmt::Write;
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for b in bytes {
+        write!(s, "{b:02x}").expect("writing to a String never fails");
+    }
+    s
+}
+
+fn generate_id<const N: usize>() -> [u8; N] {
+    static COUNTER: AtomicU64 = AtomicU64::new(1);
+
+    // Seed purely from the monotonic counter, not wall-clock time: `SystemTime::now()`'s
+    // resolution is coarser than the rate at which this can be called, so bursts of calls used
+    // to share a `nanos` reading and collide despite the counter itself being unique per call.
+    // splitmix64 (https://prng.di.unimi.it/splitmix64.c) turns that unique seed into a unique,
+    // well-mixed stream of output words.
+    let mut x = COUNTER.fetch_add(1, Ordering::Relaxed);
+
+    let mut out = [0u8; N];
+    let mut written = 0;
+    while written < N {
+        x = x.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        let bytes = z.to_le_bytes();
+        let take = (N - written).min(bytes.len());
+        out[written..written + take].copy_from_slice(&bytes[..take]);
+        written += take;
+    }
+    out
+}
+
+/// [`MakeSpan`] that extracts an incoming [W3C Trace Context] `traceparent` header and
+/// records it on the span produced by an inner [`MakeSpan`] (by default [`DefaultMakeSpan`]),
+/// establishing it as a remote child span.
+///
+/// [`DefaultMakeSpan`] already declares `trace_id`, `span_id` and `trace_sampled` as empty
+/// fields, so `TraceLayer::new_for_http().with_trace_context_propagation()` records the
+/// incoming context out of the box. If you supply your own `MakeSpan` via
+/// [`TraceLayer::make_span_with`] *before* calling `with_trace_context_propagation`, it must
+/// declare those same three fields as empty (e.g. via [`tracing::field::Empty`]) for this to
+/// have any effect; otherwise `record` is a silent no-op and the span is created as usual,
+/// just without a linked parent.
+///
+/// Constructed via [`TraceLayer::with_trace_context_propagation`].
+///
+/// [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+/// [`TraceLayer::with_trace_context_propagation`]: super::TraceLayer::with_trace_context_propagation
+#[derive(Debug, Clone)]
+pub struct TraceContextMakeSpan<Inner = DefaultMakeSpan> {
+    inner: Inner,
+}
+
+impl TraceContextMakeSpan<DefaultMakeSpan> {
+    /// Create a new `TraceContextMakeSpan` wrapping a [`DefaultMakeSpan`].
+    pub fn new() -> Self {
+        Self {
+            inner: DefaultMakeSpan::new(),
+        }
+    }
+}
+
+impl Default for TraceContextMakeSpan<DefaultMakeSpan> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<Inner> TraceContextMakeSpan<Inner> {
+    /// Create a new `TraceContextMakeSpan` wrapping a custom [`MakeSpan`].
+    pub fn with_make_span(inner: Inner) -> Self {
+        Self { inner }
+    }
+}
+
+impl<Inner, B> MakeSpan<B> for TraceContextMakeSpan<Inner>
+where
+    Inner: MakeSpan<B>,
+{
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let span = self.inner.make_span(request);
+
+        if let Some(context) = request
+            .extensions()
+            .get::<TraceContext>()
+            .copied()
+            .or_else(|| {
+                request
+                    .headers()
+                    .get(TRACEPARENT_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(TraceContext::parse_traceparent)
+            })
+        {
+            span.record("trace_id", encode_hex(&context.trace_id).as_str());
+            span.record("span_id", encode_hex(&context.span_id).as_str());
+            span.record("trace_sampled", context.sampled);
+        }
+
+        span
+    }
+}
+
+/// [`Layer`] that injects a [`TraceContext`] into outgoing requests as a `traceparent`
+/// header, for use on the client side of a call that should join a distributed trace.
+///
+/// If the request already carries a [`TraceContext`] in its extensions, that context is
+/// forwarded as-is. Otherwise a new root [`TraceContext`] is generated. Note that nothing in
+/// this module inserts a `TraceContext` into a request's extensions for you: in particular,
+/// [`TraceContextMakeSpan::make_span`] only records the extracted context onto the span it
+/// creates, since [`MakeSpan::make_span`] takes `&Request<B>` and has no way to write back
+/// into it. To continue an inbound trace on an outbound call, re-insert the extracted
+/// [`TraceContext`] into the outbound request's extensions yourself before it reaches this
+/// service.
+///
+/// Stack this *outside* (further from the transport than) a [`TraceLayer`] wrapping an
+/// outbound client, so the header is present on the request the transport actually sends.
+///
+/// [`TraceLayer`]: super::TraceLayer
+/// [`MakeSpan::make_span`]: super::MakeSpan::make_span
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PropagateTraceContextLayer {
+    _priv: (),
+}
+
+impl PropagateTraceContextLayer {
+    /// Create a new `PropagateTraceContextLayer`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for PropagateTraceContextLayer {
+    type Service = PropagateTraceContext<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        PropagateTraceContext { inner }
+    }
+}
+
+/// [`Service`] created by [`PropagateTraceContextLayer`]. See that type's docs for details.
+#[derive(Clone, Copy, Debug)]
+pub struct PropagateTraceContext<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for PropagateTraceContext<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let context = req
+            .extensions()
+            .get::<TraceContext>()
+            .copied()
+            .unwrap_or_else(TraceContext::generate);
+        req.headers_mut()
+            .insert(TRACEPARENT_HEADER, context.header_value());
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_valid_traceparent() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+        let context = TraceContext::parse_traceparent(header).unwrap();
+        assert!(context.sampled);
+        assert_eq!(context.header_value().to_str().unwrap(), header);
+    }
+
+    #[test]
+    fn rejects_malformed_traceparents() {
+        assert!(TraceContext::parse_traceparent("garbage").is_none());
+        // wrong segment lengths
+        assert!(TraceContext::parse_traceparent(
+            "0-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        )
+        .is_none());
+        // all-zero trace id is invalid
+        assert!(TraceContext::parse_traceparent(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01"
+        )
+        .is_none());
+        // reserved version
+        assert!(TraceContext::parse_traceparent(
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn unsampled_flag_round_trips() {
+        let header = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00";
+        let context = TraceContext::parse_traceparent(header).unwrap();
+        assert!(!context.sampled);
+        assert_eq!(context.header_value().to_str().unwrap(), header);
+    }
+
+    #[test]
+    fn generated_contexts_are_valid_and_distinct() {
+        let a = TraceContext::generate();
+        let b = TraceContext::generate();
+        assert_ne!(a.trace_id, b.trace_id);
+        assert_ne!(a.trace_id, [0; 16]);
+        assert_ne!(a.span_id, [0; 8]);
+    }
+
+    #[test]
+    fn generated_ids_do_not_collide_under_rapid_generation() {
+        use std::collections::HashSet;
+
+        let mut trace_ids = HashSet::new();
+        let mut span_ids = HashSet::new();
+        for _ in 0..10_000 {
+            let context = TraceContext::generate();
+            assert!(
+                trace_ids.insert(context.trace_id),
+                "trace id collided: {:?}",
+                context.trace_id
+            );
+            assert!(
+                span_ids.insert(context.span_id),
+                "span id collided: {:?}",
+                context.span_id
+            );
+        }
+    }
+}
@@ -0,0 +1,253 @@
+//! Request sampling for [`TraceLayer`].
+//!
+//! A [`Sampler`] is consulted once per request, before [`MakeSpan`] runs. When it returns
+//! `false` the layer skips span creation and every `on_*` callback for that request and just
+//! forwards it to the inner service, bounding the tracing overhead on high-throughput
+//! services.
+//!
+//! [`TraceLayer`]: super::TraceLayer
+//! [`MakeSpan`]: super::MakeSpan
+
+use super::propagation::{TraceContext, TRACEPARENT_HEADER};
+use http::Request;
+use std::{
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Decides, once per request, whether [`TraceLayer`] should create a span and run its
+/// callbacks at all.
+///
+/// [`TraceLayer`]: super::TraceLayer
+pub trait Sampler<B> {
+    /// Return `true` to trace this request as usual, `false` to skip span creation and all
+    /// `on_*` callbacks and forward the request with near-zero overhead.
+    fn sample(&mut self, request: &Request<B>) -> bool;
+}
+
+impl<F, B> Sampler<B> for F
+where
+    F: FnMut(&Request<B>) -> bool,
+{
+    fn sample(&mut self, request: &Request<B>) -> bool {
+        self(request)
+    }
+}
+
+/// The default [`Sampler`]: traces every request, preserving `TraceLayer`'s behavior prior to
+/// `.sample_with(..)` being set.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AlwaysSample {
+    _priv: (),
+}
+
+impl AlwaysSample {
+    /// Create a new `AlwaysSample`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<B> Sampler<B> for AlwaysSample {
+    fn sample(&mut self, _request: &Request<B>) -> bool {
+        true
+    }
+}
+
+/// Samples a fixed ratio of requests.
+///
+/// When the request carries a `traceparent` header, the decision is a deterministic function
+/// of its trace id, so every service along a distributed trace makes the same sampling
+/// decision for that trace. Otherwise a fresh id is generated per call, same as
+/// [`TraceContext::generate`].
+///
+/// [`TraceContext::generate`]: super::propagation::TraceContext::generate
+#[derive(Clone, Debug)]
+pub struct RatioSampler {
+    // Stored as the pre-scaled `u64` threshold so `sample` is just a comparison.
+    threshold: u64,
+}
+
+impl RatioSampler {
+    /// Create a new `RatioSampler` that samples approximately `ratio` of requests.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ratio` is not in `0.0..=1.0`.
+    pub fn new(ratio: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&ratio),
+            "ratio must be between 0.0 and 1.0, got {ratio}"
+        );
+        Self {
+            threshold: (ratio * u64::MAX as f64) as u64,
+        }
+    }
+}
+
+impl<B> Sampler<B> for RatioSampler {
+    fn sample(&mut self, request: &Request<B>) -> bool {
+        let trace_id = request
+            .extensions()
+            .get::<TraceContext>()
+            .map(|context| context.trace_id)
+            .or_else(|| {
+                request
+                    .headers()
+                    .get(TRACEPARENT_HEADER)
+                    .and_then(|value| value.to_str().ok())
+                    .and_then(TraceContext::parse_traceparent)
+                    .map(|context| context.trace_id)
+            })
+            .unwrap_or_else(|| TraceContext::generate().trace_id);
+
+        fnv1a_hash(&trace_id) < self.threshold
+    }
+}
+
+fn fnv1a_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Samples up to a fixed budget of requests per whole second, then drops the rest until the
+/// next second starts.
+///
+/// `TraceLayer`'s [`Layer::layer`] clones its `Sampler` into every `Trace` service instance it
+/// produces — when a `Trace` service is built per connection, that would silently turn a
+/// single "N per second" budget into "N per second per clone" if the budget lived in plain
+/// fields. The budget here is kept in an
+/// `Arc<Mutex<..>>` instead, so every clone of a given `PerSecondSampler` shares the same
+/// underlying window/counter and the budget stays process-wide.
+///
+/// [`Layer::layer`]: tower_layer::Layer::layer
+#[derive(Clone, Debug)]
+pub struct PerSecondSampler {
+    budget_per_second: u32,
+    state: Arc<Mutex<PerSecondState>>,
+}
+
+#[derive(Debug)]
+struct PerSecondState {
+    current_window: u64,
+    remaining: u32,
+}
+
+impl PerSecondSampler {
+    /// Create a new `PerSecondSampler` that samples at most `budget_per_second` requests in
+    /// any given second.
+    pub fn new(budget_per_second: u32) -> Self {
+        Self {
+            budget_per_second,
+            state: Arc::new(Mutex::new(PerSecondState {
+                current_window: current_unix_second(),
+                remaining: budget_per_second,
+            })),
+        }
+    }
+}
+
+impl<B> Sampler<B> for PerSecondSampler {
+    fn sample(&mut self, _request: &Request<B>) -> bool {
+        let mut state = self
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        let now = current_unix_second();
+        if now != state.current_window {
+            state.current_window = now;
+            state.remaining = self.budget_per_second;
+        }
+
+        if state.remaining == 0 {
+            false
+        } else {
+            state.remaining -= 1;
+            true
+        }
+    }
+}
+
+fn current_unix_second() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request() -> Request<()> {
+        Request::builder().body(()).unwrap()
+    }
+
+    #[test]
+    fn always_sample_always_samples() {
+        let mut sampler = AlwaysSample::new();
+        assert!(sampler.sample(&request()));
+        assert!(sampler.sample(&request()));
+    }
+
+    #[test]
+    fn ratio_zero_never_samples() {
+        let mut sampler = RatioSampler::new(0.0);
+        for _ in 0..100 {
+            assert!(!sampler.sample(&request()));
+        }
+    }
+
+    #[test]
+    fn ratio_one_always_samples() {
+        let mut sampler = RatioSampler::new(1.0);
+        for _ in 0..100 {
+            assert!(sampler.sample(&request()));
+        }
+    }
+
+    #[test]
+    fn ratio_sampler_is_deterministic_per_trace_id() {
+        let mut sampler = RatioSampler::new(0.5);
+        let mut request = request();
+        request.headers_mut().insert(
+            TRACEPARENT_HEADER,
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"
+                .parse()
+                .unwrap(),
+        );
+        let first = sampler.sample(&request);
+        let second = sampler.sample(&request);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn per_second_sampler_respects_budget_within_a_window() {
+        let mut sampler = PerSecondSampler::new(2);
+        assert!(sampler.sample(&request()));
+        assert!(sampler.sample(&request()));
+        assert!(!sampler.sample(&request()));
+    }
+
+    #[test]
+    fn per_second_sampler_shares_its_budget_across_clones() {
+        // Simulates what `TraceLayer`'s `Layer::layer` does when it clones the sampler into a
+        // `Trace` service per connection: every clone must draw from the same budget, not get
+        // its own.
+        let mut sampler = PerSecondSampler::new(2);
+        let mut cloned = sampler.clone();
+
+        assert!(sampler.sample(&request()));
+        assert!(cloned.sample(&request()));
+        assert!(!sampler.sample(&request()));
+        assert!(!cloned.sample(&request()));
+    }
+}
@@ -0,0 +1,342 @@
+use super::body::ResponseBody;
+use super::{MakeSpan, OnBodyChunk, OnEos, OnFailure, OnGrpcMessage, OnRequest, OnResponse};
+use crate::classify::{ClassifiedResponse, ClassifyResponse, MakeClassifier};
+use crate::trace::sampler::{AlwaysSample, Sampler};
+use http::{Request, Response};
+use pin_project_lite::pin_project;
+use std::{
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::Instant,
+};
+use tower_service::Service;
+use tracing::Span;
+
+/// [`Service`] produced by [`TraceLayer`](super::TraceLayer). See the [module docs](crate::trace)
+/// for an overview of how requests flow through it.
+#[derive(Debug)]
+pub struct Trace<
+    S,
+    M,
+    E,
+    MakeSpan = super::DefaultMakeSpan,
+    OnRequest = super::DefaultOnRequest,
+    OnResponse = super::DefaultOnResponse,
+    OnBodyChunk = super::DefaultOnBodyChunk,
+    OnEos = super::DefaultOnEos,
+    OnFailure = super::DefaultOnFailure,
+    OnGrpcMessage = super::DefaultOnGrpcMessage,
+    Sampler = AlwaysSample,
+> {
+    pub(crate) inner: S,
+    pub(crate) make_classifier: M,
+    pub(crate) make_span: MakeSpan,
+    pub(crate) on_request: OnRequest,
+    pub(crate) on_response: OnResponse,
+    pub(crate) on_body_chunk: OnBodyChunk,
+    pub(crate) on_eos: OnEos,
+    pub(crate) on_failure: OnFailure,
+    pub(crate) on_grpc_message: OnGrpcMessage,
+    pub(crate) sampler: Sampler,
+    pub(crate) _error: PhantomData<fn() -> E>,
+}
+
+impl<
+        S,
+        M,
+        E,
+        MakeSpanT,
+        OnRequestT,
+        OnResponseT,
+        OnBodyChunkT,
+        OnEosT,
+        OnFailureT,
+        OnGrpcMessageT,
+        SamplerT,
+        ReqBody,
+        ResBody,
+    > Service<Request<ReqBody>>
+    for Trace<
+        S,
+        M,
+        E,
+        MakeSpanT,
+        OnRequestT,
+        OnResponseT,
+        OnBodyChunkT,
+        OnEosT,
+        OnFailureT,
+        OnGrpcMessageT,
+        SamplerT,
+    >
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>, Error = E>,
+    E: fmt::Display,
+    M: MakeClassifier<E>,
+    MakeSpanT: MakeSpan<ReqBody>,
+    OnRequestT: OnRequest<ReqBody>,
+    OnResponseT: OnResponse<ResBody> + Clone,
+    OnBodyChunkT: OnBodyChunk<ResBody::Data> + Clone,
+    OnEosT: OnEos + Clone,
+    OnFailureT: OnFailure<M::FailureClass> + Clone,
+    OnGrpcMessageT: OnGrpcMessage + Clone,
+    SamplerT: Sampler<ReqBody>,
+    ResBody: http_body::Body,
+    ResBody::Error: fmt::Display,
+{
+    type Response = Response<
+        ResponseBody<ResBody, M::ClassifyEos, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT>,
+    >;
+    type Error = E;
+    type Future = ResponseFuture<
+        S::Future,
+        M::Classifier,
+        OnResponseT,
+        OnBodyChunkT,
+        OnEosT,
+        OnFailureT,
+        OnGrpcMessageT,
+    >;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if !self.sampler.sample(&req) {
+            return ResponseFuture {
+                inner: self.inner.call(req),
+                span: None,
+                classifier: None,
+                on_response: self.on_response.clone(),
+                on_body_chunk: self.on_body_chunk.clone(),
+                on_eos: self.on_eos.clone(),
+                on_failure: self.on_failure.clone(),
+                on_grpc_message: self.on_grpc_message.clone(),
+                start: Instant::now(),
+            };
+        }
+
+        let span = self.make_span.make_span(&req);
+        let classifier = self.make_classifier.make_classifier(&req);
+
+        let _guard = span.enter();
+        self.on_request.on_request(&req, &span);
+        drop(_guard);
+
+        let inner = {
+            let _guard = span.enter();
+            self.inner.call(req)
+        };
+
+        ResponseFuture {
+            inner,
+            span: Some(span),
+            classifier: Some(classifier),
+            on_response: self.on_response.clone(),
+            on_body_chunk: self.on_body_chunk.clone(),
+            on_eos: self.on_eos.clone(),
+            on_failure: self.on_failure.clone(),
+            on_grpc_message: self.on_grpc_message.clone(),
+            start: Instant::now(),
+        }
+    }
+}
+
+pin_project! {
+    /// Response future for [`Trace`].
+    pub struct ResponseFuture<F, C, OnResponseT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT> {
+        #[pin]
+        inner: F,
+        // `None` when the request was skipped by the `Sampler`: the response is forwarded
+        // untouched, with no span and no classification.
+        span: Option<Span>,
+        classifier: Option<C>,
+        on_response: OnResponseT,
+        on_body_chunk: OnBodyChunkT,
+        on_eos: OnEosT,
+        on_failure: OnFailureT,
+        on_grpc_message: OnGrpcMessageT,
+        start: Instant,
+    }
+}
+
+impl<F, ResBody, E, C, OnResponseT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT> Future
+    for ResponseFuture<F, C, OnResponseT, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+    E: fmt::Display,
+    C: ClassifyResponse,
+    OnResponseT: OnResponse<ResBody> + Clone,
+    OnBodyChunkT: OnBodyChunk<ResBody::Data> + Clone,
+    OnEosT: OnEos + Clone,
+    OnFailureT: OnFailure<C::FailureClass> + Clone,
+    OnGrpcMessageT: OnGrpcMessage + Clone,
+    ResBody: http_body::Body,
+{
+    type Output = Result<
+        Response<
+            ResponseBody<ResBody, C::ClassifyEos, OnBodyChunkT, OnEosT, OnFailureT, OnGrpcMessageT>,
+        >,
+        E,
+    >;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let Some(span) = this.span.as_ref() else {
+            // Not sampled: forward the inner response/body completely untouched.
+            return match ready!(this.inner.poll(cx)) {
+                Ok(res) => Poll::Ready(Ok(res.map(|body| {
+                    // Not sampled: don't run the user's `on_body_chunk` either, so a
+                    // sampled-out request incurs none of its (e.g. metrics/histogram) cost.
+                    ResponseBody::new(body, None, None, None, None, None, false, Span::none())
+                }))),
+                Err(err) => Poll::Ready(Err(err)),
+            };
+        };
+
+        let _guard = span.enter();
+        let result = ready!(this.inner.poll(cx));
+        let latency = this.start.elapsed();
+
+        match result {
+            Ok(res) => {
+                // The response's own Content-Type is authoritative here, not the request's:
+                // by this point we have the actual response in hand, and a request that
+                // merely carried a `application/grpc*` content type but got back e.g. a
+                // plain-text error response should not have its body parsed as gRPC framing.
+                let is_grpc = res
+                    .headers()
+                    .get(http::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.starts_with("application/grpc"));
+
+                let classifier = this.classifier.take().expect("classifier already taken");
+                match classifier.classify_response(&res) {
+                    ClassifiedResponse::Ready(classification) => {
+                        let on_failure = if let Err(failure_class) = classification {
+                            this.on_failure.on_failure(failure_class, latency, span);
+                            None
+                        } else {
+                            Some(this.on_failure.clone())
+                        };
+                        this.on_response.clone().on_response(&res, latency, span);
+                        Poll::Ready(Ok(res.map(|body| {
+                            ResponseBody::new(
+                                body,
+                                None,
+                                Some(this.on_body_chunk.clone()),
+                                None,
+                                on_failure,
+                                is_grpc.then(|| this.on_grpc_message.clone()),
+                                is_grpc,
+                                span.clone(),
+                            )
+                        })))
+                    }
+                    ClassifiedResponse::RequiresEos(classify_eos) => {
+                        this.on_response.clone().on_response(&res, latency, span);
+                        Poll::Ready(Ok(res.map(|body| {
+                            ResponseBody::new(
+                                body,
+                                Some(classify_eos),
+                                Some(this.on_body_chunk.clone()),
+                                Some(this.on_eos.clone()),
+                                Some(this.on_failure.clone()),
+                                is_grpc.then(|| this.on_grpc_message.clone()),
+                                is_grpc,
+                                span.clone(),
+                            )
+                        })))
+                    }
+                }
+            }
+            Err(err) => {
+                let classifier = this.classifier.take().expect("classifier already taken");
+                let failure_class = classifier.classify_error(&err);
+                this.on_failure.on_failure(failure_class, latency, span);
+                Poll::Ready(Err(err))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::{ServerErrorsAsFailures, SharedClassifier};
+    use crate::trace::TraceLayer;
+    use std::task::Waker;
+    use tower_layer::Layer;
+
+    /// An error type borrowing from somewhere other than `'static`, to prove `Trace` only
+    /// requires `E: fmt::Display`.
+    struct BorrowedError<'a>(&'a str);
+
+    impl fmt::Display for BorrowedError<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(self.0)
+        }
+    }
+
+    /// A body type that is never actually polled: `FailingService` always returns `Err`, so
+    /// this only needs to exist to give `Trace::Response` a concrete `ResBody`.
+    struct NeverBody;
+
+    impl http_body::Body for NeverBody {
+        type Data = bytes::Bytes;
+        type Error = std::convert::Infallible;
+
+        fn poll_frame(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+        ) -> Poll<Option<Result<http_body::Frame<Self::Data>, Self::Error>>> {
+            Poll::Ready(None)
+        }
+    }
+
+    /// A service whose `Error` borrows from a local value rather than being `'static`.
+    struct FailingService<'a>(&'a str);
+
+    impl<'a> Service<Request<()>> for FailingService<'a> {
+        type Response = Response<NeverBody>;
+        type Error = BorrowedError<'a>;
+        type Future = std::future::Ready<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _req: Request<()>) -> Self::Future {
+            std::future::ready(Err(BorrowedError(self.0)))
+        }
+    }
+
+    #[test]
+    fn accepts_a_service_with_a_non_static_error_type() {
+        let message = String::from("borrowed failure");
+        let inner = FailingService(message.as_str());
+
+        let classifier =
+            SharedClassifier::new::<BorrowedError<'_>>(ServerErrorsAsFailures::default());
+        let mut trace = TraceLayer::new(classifier).layer(inner);
+
+        let future = trace.call(Request::builder().body(()).unwrap());
+        let mut future = std::pin::pin!(future);
+
+        let waker = Waker::noop();
+        let mut cx = Context::from_waker(waker);
+        let Poll::Ready(result) = future.as_mut().poll(&mut cx) else {
+            panic!("future should resolve synchronously");
+        };
+
+        match result {
+            Ok(_) => panic!("FailingService always fails"),
+            Err(err) => assert_eq!(err.0, "borrowed failure"),
+        }
+    }
+}